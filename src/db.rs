@@ -0,0 +1,273 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use std::sync::{Arc, Mutex};
+
+/// The state of a single hook run, persisted across restarts.
+///
+/// Transitions are `Pending -> Running -> Finished { success }`. `Error` is
+/// reserved for runs that never reached a `Finished` outcome, e.g. the worker
+/// panicking mid-run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+    Pending,
+    Running,
+    Finished { success: bool },
+    Error,
+}
+
+impl JobState {
+    fn as_str(&self) -> &'static str {
+        match self {
+            JobState::Pending => "pending",
+            JobState::Running => "running",
+            JobState::Finished { success: true } => "finished_success",
+            JobState::Finished { success: false } => "finished_failure",
+            JobState::Error => "error",
+        }
+    }
+
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(JobState::Pending),
+            "running" => Some(JobState::Running),
+            "finished_success" => Some(JobState::Finished { success: true }),
+            "finished_failure" => Some(JobState::Finished { success: false }),
+            "error" => Some(JobState::Error),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DbError {
+    SqliteError(rusqlite::Error),
+}
+
+impl From<rusqlite::Error> for DbError {
+    fn from(e: rusqlite::Error) -> Self {
+        DbError::SqliteError(e)
+    }
+}
+
+impl std::fmt::Display for DbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DbError::SqliteError(e) => write!(f, "sqlite error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DbError {}
+
+/// A job row left over from a previous run of the server, found on startup.
+#[derive(Debug, Clone)]
+pub struct JobRow {
+    pub id: i64,
+    pub hook_name: String,
+    pub payload: String,
+}
+
+/// Handle onto the sqlite-backed job table. Cheap to clone - the connection
+/// is shared behind an `Arc<Mutex<_>>` so it can be handed to both the http
+/// handlers and the worker thread.
+#[derive(Debug, Clone)]
+pub struct DbCtx {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl DbCtx {
+    pub fn open(path: &str) -> Result<Self, DbError> {
+        let conn = Connection::open(path)?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS jobs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                hook_name TEXT NOT NULL,
+                repo_full_name TEXT NOT NULL,
+                git_ref TEXT NOT NULL,
+                after_sha TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                state TEXT NOT NULL,
+                log TEXT,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS jobs_dedup ON jobs (hook_name, repo_full_name, git_ref, after_sha)",
+            [],
+        )?;
+        Ok(DbCtx {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Records a push as a new job, keyed by (hook name, repo, ref, sha) so
+    /// the same commit is never *inserted* twice. Returns the row's id and
+    /// its current `JobState` - for a webhook redelivery of a commit that's
+    /// already `Running`/`Finished`, callers must check the state and skip
+    /// re-running the job rather than assuming a fresh `Pending` row.
+    pub fn insert_job(
+        &self,
+        hook_name: &str,
+        repo_full_name: &str,
+        git_ref: &str,
+        after_sha: &str,
+        payload: &str,
+    ) -> Result<(i64, JobState), DbError> {
+        let conn = self.conn.lock().unwrap();
+        let existing: Option<(i64, String)> = conn
+            .query_row(
+                "SELECT id, state FROM jobs WHERE hook_name = ?1 AND repo_full_name = ?2 AND git_ref = ?3 AND after_sha = ?4",
+                params![hook_name, repo_full_name, git_ref, after_sha],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        if let Some((id, state)) = existing {
+            let state = JobState::from_str(&state).unwrap_or(JobState::Error);
+            return Ok((id, state));
+        }
+        conn.execute(
+            "INSERT INTO jobs (hook_name, repo_full_name, git_ref, after_sha, payload, state) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                hook_name,
+                repo_full_name,
+                git_ref,
+                after_sha,
+                payload,
+                JobState::Pending.as_str()
+            ],
+        )?;
+        Ok((conn.last_insert_rowid(), JobState::Pending))
+    }
+
+    pub fn set_state(&self, id: i64, state: JobState) -> Result<(), DbError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE jobs SET state = ?1, updated_at = datetime('now') WHERE id = ?2",
+            params![state.as_str(), id],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_log(&self, id: i64, log: &str) -> Result<(), DbError> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE jobs SET log = ?1 WHERE id = ?2",
+            params![log, id],
+        )?;
+        Ok(())
+    }
+
+    /// Rows left in `Pending` or `Running` state, i.e. jobs that were in
+    /// flight when the server last stopped. Used on startup to re-enqueue
+    /// work lost to a crash or restart.
+    pub fn unfinished_jobs(&self) -> Result<Vec<JobRow>, DbError> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, hook_name, payload FROM jobs WHERE state IN (?1, ?2) ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map(
+                params![JobState::Pending.as_str(), JobState::Running.as_str()],
+                |row| {
+                    Ok(JobRow {
+                        id: row.get(0)?,
+                        hook_name: row.get(1)?,
+                        payload: row.get(2)?,
+                    })
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_db() -> DbCtx {
+        DbCtx::open(":memory:").unwrap()
+    }
+
+    #[test]
+    fn insert_job_dedups_on_hook_repo_ref_sha() {
+        let db = test_db();
+        let (id1, _) = db
+            .insert_job("hook", "org/repo", "refs/heads/main", "abc123", "{}")
+            .unwrap();
+        let (id2, _) = db
+            .insert_job("hook", "org/repo", "refs/heads/main", "abc123", "{}")
+            .unwrap();
+        assert_eq!(id1, id2);
+    }
+
+    #[test]
+    fn insert_job_distinguishes_different_shas() {
+        let db = test_db();
+        let (id1, _) = db
+            .insert_job("hook", "org/repo", "refs/heads/main", "abc123", "{}")
+            .unwrap();
+        let (id2, _) = db
+            .insert_job("hook", "org/repo", "refs/heads/main", "def456", "{}")
+            .unwrap();
+        assert_ne!(id1, id2);
+    }
+
+    #[test]
+    fn insert_job_reports_pending_for_a_new_row() {
+        let db = test_db();
+        let (_, state) = db
+            .insert_job("hook", "org/repo", "refs/heads/main", "abc123", "{}")
+            .unwrap();
+        assert_eq!(state, JobState::Pending);
+    }
+
+    #[test]
+    fn insert_job_reports_the_existing_row_s_current_state() {
+        // A webhook redelivery of an already-built SHA must see that the
+        // job has finished, not be told it's a fresh Pending row.
+        let db = test_db();
+        let (id, _) = db
+            .insert_job("hook", "org/repo", "refs/heads/main", "abc123", "{}")
+            .unwrap();
+        db.set_state(id, JobState::Finished { success: true })
+            .unwrap();
+
+        let (redelivered_id, state) = db
+            .insert_job("hook", "org/repo", "refs/heads/main", "abc123", "{}")
+            .unwrap();
+        assert_eq!(redelivered_id, id);
+        assert_eq!(state, JobState::Finished { success: true });
+    }
+
+    #[test]
+    fn unfinished_jobs_excludes_finished() {
+        let db = test_db();
+        let (pending_id, _) = db
+            .insert_job("hook", "org/repo", "refs/heads/main", "abc123", "{}")
+            .unwrap();
+        let (done_id, _) = db
+            .insert_job("hook", "org/repo", "refs/heads/main", "def456", "{}")
+            .unwrap();
+        db.set_state(done_id, JobState::Finished { success: true })
+            .unwrap();
+
+        let unfinished = db.unfinished_jobs().unwrap();
+        assert_eq!(unfinished.len(), 1);
+        assert_eq!(unfinished[0].id, pending_id);
+    }
+
+    #[test]
+    fn unfinished_jobs_includes_running() {
+        let db = test_db();
+        let (id, _) = db
+            .insert_job("hook", "org/repo", "refs/heads/main", "abc123", "{}")
+            .unwrap();
+        db.set_state(id, JobState::Running).unwrap();
+
+        let unfinished = db.unfinished_jobs().unwrap();
+        assert_eq!(unfinished.len(), 1);
+        assert_eq!(unfinished[0].id, id);
+    }
+}
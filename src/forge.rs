@@ -0,0 +1,315 @@
+use serde::Deserialize;
+use tide::Request;
+
+use crate::github::GithubPushSummary;
+
+/// A push event, normalized to the handful of fields every forge can supply,
+/// regardless of how its webhook payload is actually shaped.
+#[derive(Debug, Clone)]
+pub struct PushInfo {
+    pub repo_full_name: String,
+    pub git_ref: String,
+    pub after_sha: String,
+    pub pusher: String,
+}
+
+/// Picks `key` out of a JSON object, reporting its path on failure - the
+/// same "missing element at '$.path'" shape as `GithubHookError` so a
+/// schema change on any forge surfaces as a 400 with a JSON path rather
+/// than a silent "no hook matched".
+fn json_child<'a>(v: &'a serde_json::Value, parent_path: &str, key: &str) -> Result<&'a serde_json::Value, String> {
+    v.get(key)
+        .ok_or_else(|| format!("missing element at '{}.{}'", parent_path, key))
+}
+
+fn json_str<'a>(v: &'a serde_json::Value, path: &str) -> Result<&'a str, String> {
+    v.as_str()
+        .ok_or_else(|| format!("element at '{}' is not a string", path))
+}
+
+/// A source of webhooks - github.com, a self-hosted GitLab, a Gitea/Forgejo
+/// instance, etc. Each forge knows its own signing scheme and push payload
+/// shape; everything downstream of `process_request` only deals in
+/// `PushInfo`.
+pub trait Forge: std::fmt::Debug {
+    fn verify_signature(&self, req: &Request<()>, body: &[u8], secret: &str) -> Result<(), String>;
+
+    /// Parses `body`, returning `Ok(None)` if it isn't recognized as this
+    /// forge's push payload at all, or `Err` if it is but some field the
+    /// server needs is missing or malformed.
+    fn parse_push(&self, body: &[u8]) -> Result<Option<PushInfo>, String>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GithubForge;
+
+impl Forge for GithubForge {
+    fn verify_signature(&self, req: &Request<()>, body: &[u8], secret: &str) -> Result<(), String> {
+        // signature = 'sha256=' + OpenSSL::HMAC.hexdigest(OpenSSL::Digest.new('sha256'), ENV['SECRET_TOKEN'], payload_body)
+        // return halt 500, "Signatures didn't match!" unless Rack::Utils.secure_compare(signature, request.env['HTTP_X_HUB_SIGNATURE_256'])
+
+        let signature = req
+            .header("X-Hub-Signature-256")
+            .ok_or_else(|| "Missing X-Hub-Signature-256 header".to_string())?
+            .last()
+            .as_str();
+
+        let signature = signature.strip_prefix("sha256=").ok_or_else(|| {
+            format!(
+                "Malformed X-Hub-Signature-256: should start with sha256= but was '{}'",
+                signature
+            )
+        })?;
+
+        let signature_bytes = hex::decode(signature).map_err(|_| {
+            format!(
+                "Malformed X-Hub-Signature-256: should be all hex, but was '{}'",
+                signature
+            )
+        })?;
+
+        use ring::hmac;
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+        hmac::verify(&key, body, &signature_bytes)
+            .map_err(|_| "Invalid message signature".to_string())?;
+        Ok(())
+    }
+
+    /// Never returns `Ok(None)`: GitHub's push body has no discriminator
+    /// field, so every body is read as a GitHub push and one that isn't
+    /// JSON or lacks a required field is an `Err`.
+    fn parse_push(&self, body: &[u8]) -> Result<Option<PushInfo>, String> {
+        let summary = match GithubPushSummary::parse(body) {
+            Ok(summary) => summary,
+            Err(e) => return Err(e.to_string()),
+        };
+        Ok(Some(PushInfo {
+            repo_full_name: summary.repo_full_name,
+            git_ref: summary.git_ref,
+            after_sha: summary.after_sha,
+            pusher: summary.pusher.unwrap_or_default(),
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GitlabForge;
+
+impl Forge for GitlabForge {
+    fn verify_signature(&self, req: &Request<()>, _body: &[u8], secret: &str) -> Result<(), String> {
+        let token = req
+            .header("X-Gitlab-Token")
+            .ok_or_else(|| "Missing X-Gitlab-Token header".to_string())?
+            .last()
+            .as_str();
+
+        ring::constant_time::verify_slices_are_equal(token.as_bytes(), secret.as_bytes())
+            .map_err(|_| "X-Gitlab-Token did not match configured secret".to_string())?;
+        Ok(())
+    }
+
+    fn parse_push(&self, body: &[u8]) -> Result<Option<PushInfo>, String> {
+        let v: serde_json::Value = match serde_json::from_slice(body) {
+            Ok(v) => v,
+            Err(_) => return Ok(None),
+        };
+        if v.get("object_kind").and_then(|v| v.as_str()) != Some("push") {
+            return Ok(None);
+        }
+
+        let project = json_child(&v, "$", "project")?;
+        let repo_full_name = json_str(
+            json_child(project, "$.project", "path_with_namespace")?,
+            "$.project.path_with_namespace",
+        )?
+        .to_string();
+        let git_ref = json_str(json_child(&v, "$", "ref")?, "$.ref")?.to_string();
+        let after_sha = json_str(json_child(&v, "$", "after")?, "$.after")?.to_string();
+        let pusher = v
+            .get("user_username")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+
+        Ok(Some(PushInfo {
+            repo_full_name,
+            git_ref,
+            after_sha,
+            pusher,
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GiteaForge;
+
+impl Forge for GiteaForge {
+    fn verify_signature(&self, req: &Request<()>, body: &[u8], secret: &str) -> Result<(), String> {
+        let signature = req
+            .header("X-Gitea-Signature")
+            .ok_or_else(|| "Missing X-Gitea-Signature header".to_string())?
+            .last()
+            .as_str();
+
+        let signature_bytes = hex::decode(signature).map_err(|_| {
+            format!(
+                "Malformed X-Gitea-Signature: should be all hex, but was '{}'",
+                signature
+            )
+        })?;
+
+        use ring::hmac;
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+        hmac::verify(&key, body, &signature_bytes)
+            .map_err(|_| "Invalid message signature".to_string())?;
+        Ok(())
+    }
+
+    fn parse_push(&self, body: &[u8]) -> Result<Option<PushInfo>, String> {
+        let v: serde_json::Value = match serde_json::from_slice(body) {
+            Ok(v) => v,
+            Err(_) => return Ok(None),
+        };
+        // Gitea/Forgejo's push payload is modeled on GitHub's, down to the
+        // same top-level keys, and carries no discriminator field of its
+        // own. Require the same minimal shape before treating anything
+        // else as a Gitea-specific parse failure.
+        if v.get("repository").is_none() || v.get("ref").is_none() || v.get("after").is_none() {
+            return Ok(None);
+        }
+
+        let repository = json_child(&v, "$", "repository")?;
+        let repo_full_name = json_str(
+            json_child(repository, "$.repository", "full_name")?,
+            "$.repository.full_name",
+        )?
+        .to_string();
+        let git_ref = json_str(json_child(&v, "$", "ref")?, "$.ref")?.to_string();
+        let after_sha = json_str(json_child(&v, "$", "after")?, "$.after")?.to_string();
+        let pusher = v
+            .get("pusher")
+            .and_then(|p| p.get("username"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+
+        Ok(Some(PushInfo {
+            repo_full_name,
+            git_ref,
+            after_sha,
+            pusher,
+        }))
+    }
+}
+
+/// Which forge a hook's webhooks come from, as configured per-hook.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ForgeType {
+    Github,
+    Gitlab,
+    Gitea,
+}
+
+impl Default for ForgeType {
+    fn default() -> Self {
+        ForgeType::Github
+    }
+}
+
+impl ForgeType {
+    pub fn forge(&self) -> Box<dyn Forge> {
+        match self {
+            ForgeType::Github => Box::new(GithubForge),
+            ForgeType::Gitlab => Box::new(GitlabForge),
+            ForgeType::Gitea => Box::new(GiteaForge),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gitlab_parses_push_event() {
+        let body = serde_json::json!({
+            "object_kind": "push",
+            "ref": "refs/heads/main",
+            "after": "abc123",
+            "user_username": "alice",
+            "project": { "path_with_namespace": "org/repo" },
+        })
+        .to_string();
+        let info = GitlabForge.parse_push(body.as_bytes()).unwrap().unwrap();
+        assert_eq!(info.repo_full_name, "org/repo");
+        assert_eq!(info.git_ref, "refs/heads/main");
+        assert_eq!(info.after_sha, "abc123");
+        assert_eq!(info.pusher, "alice");
+    }
+
+    #[test]
+    fn gitlab_ignores_non_push_events() {
+        let body = serde_json::json!({ "object_kind": "tag_push" }).to_string();
+        assert!(GitlabForge.parse_push(body.as_bytes()).unwrap().is_none());
+    }
+
+    #[test]
+    fn gitlab_ignores_unparseable_body() {
+        assert!(GitlabForge.parse_push(b"not json").unwrap().is_none());
+    }
+
+    #[test]
+    fn gitlab_errors_on_a_push_event_missing_a_required_field() {
+        // Recognized as a GitLab push (object_kind == "push") but missing
+        // `ref` - a schema change worth a 400, not a silent "no match".
+        let body = serde_json::json!({
+            "object_kind": "push",
+            "after": "abc123",
+            "project": { "path_with_namespace": "org/repo" },
+        })
+        .to_string();
+        assert!(GitlabForge.parse_push(body.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn gitea_parses_push_event() {
+        let body = serde_json::json!({
+            "ref": "refs/heads/main",
+            "after": "abc123",
+            "pusher": { "username": "bob" },
+            "repository": { "full_name": "org/repo" },
+        })
+        .to_string();
+        let info = GiteaForge.parse_push(body.as_bytes()).unwrap().unwrap();
+        assert_eq!(info.repo_full_name, "org/repo");
+        assert_eq!(info.git_ref, "refs/heads/main");
+        assert_eq!(info.after_sha, "abc123");
+        assert_eq!(info.pusher, "bob");
+    }
+
+    #[test]
+    fn gitea_ignores_unparseable_body() {
+        assert!(GiteaForge.parse_push(b"not json").unwrap().is_none());
+    }
+
+    #[test]
+    fn gitea_ignores_a_body_missing_the_minimal_shape() {
+        let body = serde_json::json!({ "ref": "refs/heads/main" }).to_string();
+        assert!(GiteaForge.parse_push(body.as_bytes()).unwrap().is_none());
+    }
+
+    #[test]
+    fn gitea_errors_on_a_push_event_missing_a_required_field() {
+        // Recognized as a Gitea push (has repository/ref/after) but
+        // `repository.full_name` is missing - a schema change worth a
+        // 400, not a silent "no match".
+        let body = serde_json::json!({
+            "ref": "refs/heads/main",
+            "after": "abc123",
+            "repository": {},
+        })
+        .to_string();
+        assert!(GiteaForge.parse_push(body.as_bytes()).is_err());
+    }
+}
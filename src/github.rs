@@ -1,171 +1,100 @@
-use serde::Deserialize;
-
-#[derive(Deserialize, Debug)]
-pub struct GitHubSHA(String);
-
-#[derive(Deserialize, Debug)]
-pub struct GitHubRef(pub String);
-
-#[derive(Deserialize, Debug)]
-pub struct GitHubURL(String);
-
-#[derive(Deserialize, Debug)]
-#[serde(deny_unknown_fields)]
-pub struct GitUser {
-    email: Option<String>,
-    name: Option<String>,
-    username: Option<String>,
+use serde_json::Value;
+
+/// Error produced while picking fields out of a raw webhook payload.
+/// Carries the JSON path of the offending element so a malformed or
+/// schema-evolved delivery can be reported back as a clear 400 instead of
+/// an opaque 500.
+#[derive(Debug)]
+pub enum GithubHookError {
+    MissingElement { path: String },
+    BadType { path: String, expected: &'static str },
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(deny_unknown_fields)]
-pub struct GitHubCommit {
-    // Changes to files
-    added: Vec<String>,
-    modified: Vec<String>,
-    removed: Vec<String>,
-
-    id: GitHubSHA,
-    author: GitUser,
-    committer: GitUser,
-
-    message: String,
-    distinct: bool,
-    timestamp: String,
-    tree_id: GitHubSHA,
-    url: String,
+impl std::fmt::Display for GithubHookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GithubHookError::MissingElement { path } => {
+                write!(f, "missing element at '{}'", path)
+            }
+            GithubHookError::BadType { path, expected } => {
+                write!(f, "element at '{}' is not a {}", path, expected)
+            }
+        }
+    }
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(deny_unknown_fields)]
-pub struct GitHubRepository {
-    pub archive_url: GitHubURL,
-    pub archived: bool,
-    pub assignees_url: GitHubURL,
-    pub blobs_url: GitHubURL,
-    pub branches_url: GitHubURL,
-    pub clone_url: GitHubURL,
-    pub collaborators_url: GitHubURL,
-    pub comments_url: GitHubURL,
-    pub commits_url: GitHubURL,
-    pub compare_url: GitHubURL,
-    pub contents_url: GitHubURL,
-    pub contributors_url: GitHubURL,
-    pub created_at: u64,
-    pub default_branch: String,
-    pub deployments_url: GitHubURL,
-    pub description: String,
-    pub disabled: bool,
-    pub downloads_url: GitHubURL,
-    pub events_url: GitHubURL,
-    pub fork: bool,
-    pub forks: usize,
-    pub forks_count: usize,
-    pub forks_url: GitHubURL,
-    pub full_name: String,
-    pub git_commits_url: GitHubURL,
-    pub git_refs_url: GitHubURL,
-    pub git_tags_url: GitHubURL,
-    pub git_url: GitHubURL,
-    pub has_downloads: bool,
-    pub has_issues: bool,
-    pub has_pages: bool,
-    pub has_projects: bool,
-    pub has_wiki: bool,
-    pub homepage: Option<GitHubURL>,
-    pub hooks_url: GitHubURL,
-    pub html_url: GitHubURL,
-    pub id: u64,
-    pub issue_comment_url: GitHubURL,
-    pub issue_events_url: GitHubURL,
-    pub issues_url: GitHubURL,
-    pub keys_url: GitHubURL,
-    pub labels_url: GitHubURL,
-    pub language: String,
-    pub languages_url: GitHubURL,
-    pub license: Option<String>,
-    pub master_branch: String,
-    pub merges_url: GitHubURL,
-    pub milestones_url: GitHubURL,
-    pub mirror_url: Option<GitHubURL>,
-    pub name: String,
-    pub node_id: String,
-    pub notifications_url: GitHubURL,
-    pub open_issues: usize,
-    pub open_issues_count: usize,
-    pub owner: GitHubUser,
-    pub private: bool,
-    pub pulls_url: GitHubURL,
-    pub pushed_at: u64,
-    pub releases_url: GitHubURL,
-    pub size: usize,
-    pub ssh_url: GitHubURL,
-    pub stargazers: usize,
-    pub stargazers_count: usize,
-    pub stargazers_url: GitHubURL,
-    pub statuses_url: GitHubURL,
-    pub subscribers_url: GitHubURL,
-    pub subscription_url: GitHubURL,
-    pub svn_url: GitHubURL,
-    pub tags_url: GitHubURL,
-    pub teams_url: GitHubURL,
-    pub trees_url: GitHubURL,
-    pub updated_at: String,
-    pub url: GitHubURL,
-    pub watchers: usize,
-    pub watchers_count: usize,
+fn child<'a>(v: &'a Value, parent_path: &str, key: &str) -> Result<&'a Value, GithubHookError> {
+    v.get(key).ok_or_else(|| GithubHookError::MissingElement {
+        path: format!("{}.{}", parent_path, key),
+    })
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(deny_unknown_fields)]
-pub struct GitHubUser {
-    avatar_url: GitHubURL,
-    email: Option<String>,
-    events_url: GitHubURL,
-    followers_url: GitHubURL,
-    following_url: GitHubURL,
-    gists_url: GitHubURL,
-    gravatar_id: String,
-    html_url: GitHubURL,
-    id: u64,
-    login: String,
-    name: Option<String>,
-    node_id: String,
-    organizations_url: GitHubURL,
-    received_events_url: GitHubURL,
-    repos_url: GitHubURL,
-    site_admin: bool,
-    starred_url: GitHubURL,
-    subscriptions_url: GitHubURL,
-    #[serde(rename = "type")]
-    user_type: String,
-    url: GitHubURL,
+fn as_str<'a>(v: &'a Value, path: &str) -> Result<&'a str, GithubHookError> {
+    v.as_str().ok_or_else(|| GithubHookError::BadType {
+        path: path.to_string(),
+        expected: "string",
+    })
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(deny_unknown_fields)]
-
-pub struct GithubPushEvent {
-    pub after: Option<GitHubSHA>,
-    pub before: Option<GitHubSHA>,
-
-    pub base_ref: Option<GitHubRef>,
-    pub commits: Option<Vec<GitHubCommit>>,
-
-    pub compare: Option<GitHubURL>,
-
-    pub created: bool,
-    pub deleted: bool,
-    pub forced: bool,
-
-    pub head_commit: GitHubCommit,
-    pub pusher: GitUser,
-
-    #[serde(rename = "ref")]
-    pub reference: GitHubRef,
+/// The handful of fields the server needs from a GitHub `push` webhook,
+/// picked out of raw JSON rather than deserialized via an exhaustive struct.
+/// GitHub adds fields to this payload from time to time, and a struct with
+/// `#[serde(deny_unknown_fields)]` would turn any one of those additions
+/// into a decoding error for every delivery. Only `repository.full_name`,
+/// `ref` and `after` are mandatory; `head_commit` is absent (or `null`) on
+/// pushes that delete a branch, so it's read best-effort for the
+/// notification summary rather than required.
+#[derive(Debug, Clone)]
+pub struct GithubPushSummary {
+    pub repo_full_name: String,
+    pub git_ref: String,
+    pub after_sha: String,
+    pub pusher: Option<String>,
+    pub head_commit_message: Option<String>,
+    pub head_commit_author: Option<String>,
+}
 
-    pub repository: GitHubRepository,
-    pub sender: GitHubUser,
+impl GithubPushSummary {
+    pub fn parse(body: &[u8]) -> Result<Self, GithubHookError> {
+        let root: Value = serde_json::from_slice(body).map_err(|_| GithubHookError::BadType {
+            path: "$".to_string(),
+            expected: "json object",
+        })?;
+
+        let repository = child(&root, "$", "repository")?;
+        let repo_full_name =
+            as_str(child(repository, "$.repository", "full_name")?, "$.repository.full_name")?
+                .to_string();
+
+        let git_ref = as_str(child(&root, "$", "ref")?, "$.ref")?.to_string();
+        let after_sha = as_str(child(&root, "$", "after")?, "$.after")?.to_string();
+
+        let head_commit = root.get("head_commit").filter(|v| !v.is_null());
+        let head_commit_message = head_commit
+            .and_then(|hc| hc.get("message"))
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let head_commit_author = head_commit
+            .and_then(|hc| hc.get("author"))
+            .and_then(|a| a.get("name"))
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        let pusher = root
+            .get("pusher")
+            .and_then(|p| p.get("username"))
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        Ok(GithubPushSummary {
+            repo_full_name,
+            git_ref,
+            after_sha,
+            pusher,
+            head_commit_message,
+            head_commit_author,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -173,40 +102,94 @@ pub mod test {
 
     use super::*;
 
-    pub fn github_push_event_str() -> String {
-        include_str!("../test_data/github_push_event.json").into()
+    fn push_summary_json(head_commit: serde_json::Value) -> String {
+        serde_json::json!({
+            "ref": "refs/heads/main",
+            "after": "abc123",
+            "pusher": { "name": "Alice", "username": "alice" },
+            "repository": { "full_name": "org/repo" },
+            "head_commit": head_commit,
+        })
+        .to_string()
     }
 
-    pub fn github_commit_entry_str() -> String {
-        include_str!("../test_data/github_commit_entry.json").into()
-    }
-
-    pub fn github_repository_entry_str() -> String {
-        include_str!("../test_data/github_repository_entry.json").into()
+    #[test]
+    fn push_summary_parses_happy_path() {
+        let body = push_summary_json(serde_json::json!({
+            "message": "fix bug",
+            "author": { "name": "Alice" },
+        }));
+        let summary = GithubPushSummary::parse(body.as_bytes()).unwrap();
+        assert_eq!(summary.repo_full_name, "org/repo");
+        assert_eq!(summary.git_ref, "refs/heads/main");
+        assert_eq!(summary.after_sha, "abc123");
+        assert_eq!(summary.pusher.as_deref(), Some("alice"));
+        assert_eq!(summary.head_commit_message.as_deref(), Some("fix bug"));
+        assert_eq!(summary.head_commit_author.as_deref(), Some("Alice"));
     }
 
-    pub fn github_user_entry_str() -> String {
-        include_str!("../test_data/github_user_entry.json").into()
+    #[test]
+    fn push_summary_tolerates_missing_head_commit() {
+        // e.g. a push that deletes a branch carries no head_commit at all.
+        let body = serde_json::json!({
+            "ref": "refs/heads/main",
+            "after": "0000000000000000000000000000000000000000",
+            "repository": { "full_name": "org/repo" },
+        })
+        .to_string();
+        let summary = GithubPushSummary::parse(body.as_bytes()).unwrap();
+        assert_eq!(summary.head_commit_message, None);
+        assert_eq!(summary.head_commit_author, None);
     }
 
     #[test]
-    pub fn deserialize_github_push_event() {
-        let _event: GithubPushEvent = serde_json::from_str(&github_push_event_str()).unwrap();
+    fn push_summary_tolerates_null_head_commit() {
+        let body = push_summary_json(serde_json::Value::Null);
+        let summary = GithubPushSummary::parse(body.as_bytes()).unwrap();
+        assert_eq!(summary.head_commit_message, None);
     }
 
     #[test]
-    pub fn deserialize_github_commit_entry() {
-        let _commit: GitHubCommit = serde_json::from_str(&&github_commit_entry_str()).unwrap();
+    fn push_summary_tolerates_unknown_fields() {
+        let body = serde_json::json!({
+            "ref": "refs/heads/main",
+            "after": "abc123",
+            "repository": { "full_name": "org/repo", "a_field_github_added_later": true },
+            "a_top_level_field_github_added_later": 42,
+        })
+        .to_string();
+        assert!(GithubPushSummary::parse(body.as_bytes()).is_ok());
     }
 
     #[test]
-    pub fn deserialize_github_repository_entry() {
-        let _commit: GitHubRepository =
-            serde_json::from_str(&github_repository_entry_str()).unwrap();
+    fn push_summary_reports_missing_element() {
+        let body = serde_json::json!({
+            "ref": "refs/heads/main",
+            "after": "abc123",
+        })
+        .to_string();
+        match GithubPushSummary::parse(body.as_bytes()) {
+            Err(GithubHookError::MissingElement { path }) => {
+                assert_eq!(path, "$.repository");
+            }
+            other => panic!("expected MissingElement, got {:?}", other),
+        }
     }
 
     #[test]
-    pub fn deserialize_github_user_entry() {
-        let _commit: GitHubUser = serde_json::from_str(&github_user_entry_str()).unwrap();
+    fn push_summary_reports_bad_type() {
+        let body = serde_json::json!({
+            "ref": "refs/heads/main",
+            "after": "abc123",
+            "repository": { "full_name": 123 },
+        })
+        .to_string();
+        match GithubPushSummary::parse(body.as_bytes()) {
+            Err(GithubHookError::BadType { path, expected }) => {
+                assert_eq!(path, "$.repository.full_name");
+                assert_eq!(expected, "string");
+            }
+            other => panic!("expected BadType, got {:?}", other),
+        }
     }
 }
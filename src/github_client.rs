@@ -0,0 +1,89 @@
+use serde::Serialize;
+
+/// The states GitHub's Commit Status API accepts.
+#[derive(Debug, Clone, Copy)]
+pub enum StatusState {
+    Pending,
+    Success,
+    Failure,
+    Error,
+}
+
+impl StatusState {
+    fn as_str(&self) -> &'static str {
+        match self {
+            StatusState::Pending => "pending",
+            StatusState::Success => "success",
+            StatusState::Failure => "failure",
+            StatusState::Error => "error",
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct CreateStatusBody<'a> {
+    state: &'a str,
+    description: &'a str,
+    context: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_url: Option<&'a str>,
+}
+
+#[derive(Debug)]
+pub enum GithubClientError {
+    RequestError(String),
+    UnexpectedStatus { status: u16, body: String },
+}
+
+/// Minimal client for the parts of the GitHub REST API this server needs -
+/// right now just posting Commit Statuses back onto a pushed SHA so the
+/// push shows up green/red on github.com.
+pub struct GithubClient {
+    token: String,
+}
+
+impl GithubClient {
+    pub fn new(token: String) -> Self {
+        GithubClient { token }
+    }
+
+    /// Posts a commit status to `statuses_url` - the per-commit Commit
+    /// Status endpoint, with `{sha}` already substituted for the commit
+    /// being built.
+    pub async fn create_status(
+        &self,
+        statuses_url: &str,
+        state: StatusState,
+        description: &str,
+        context: &str,
+        target_url: Option<&str>,
+    ) -> Result<(), GithubClientError> {
+        let body = CreateStatusBody {
+            state: state.as_str(),
+            description,
+            context,
+            target_url,
+        };
+
+        let req_body = surf::Body::from_json(&body)
+            .map_err(|e| GithubClientError::RequestError(e.to_string()))?;
+
+        let mut response = surf::post(statuses_url)
+            .header("Authorization", format!("Bearer {}", self.token).as_str())
+            .header("Accept", "application/vnd.github+json")
+            .body(req_body)
+            .await
+            .map_err(|e| GithubClientError::RequestError(e.to_string()))?;
+
+        // surf only returns Err for transport failures - a rejected status
+        // post (bad token, unknown sha, ...) comes back as an Ok response
+        // with a 4xx/5xx status, so that has to be checked explicitly.
+        if !response.status().is_success() {
+            let status = response.status() as u16;
+            let body = response.body_string().await.unwrap_or_default();
+            return Err(GithubClientError::UnexpectedStatus { status, body });
+        }
+
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 use git::CommandOutput;
 use git::GitRepositoryError;
@@ -14,11 +14,19 @@ use std::ffi::OsStr;
 use std::sync::Mutex;
 use std::sync::mpsc;
 
+use crate::db::{DbCtx, JobState};
+use crate::forge::{ForgeType, PushInfo};
 use crate::git::GitRepository;
-use crate::github::GithubPushEvent;
+use crate::github::GithubPushSummary;
+use crate::github_client::{GithubClient, StatusState};
+use crate::notify::NotifyConfig;
 
+pub mod db;
+pub mod forge;
 pub mod git;
 pub mod github;
+pub mod github_client;
+pub mod notify;
 
 #[derive(Deserialize, Debug, Clone)]
 #[serde(deny_unknown_fields)]
@@ -30,27 +38,70 @@ struct HookConfig {
     script: String,
     branch: String,
     secret: Option<String>,
+    github_token: Option<String>,
+    #[serde(default)]
+    forge_type: ForgeType,
+    notify: Option<NotifyConfig>,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
 struct Config {
+    #[serde(default = "default_db_path")]
+    db_path: String,
+    #[serde(default)]
+    server: ServerConfig,
     hooks: Vec<HookConfig>,
 }
 
+fn default_db_path() -> String {
+    "webhook_server.sqlite3".to_string()
+}
+
+/// Where and how the server listens. Plaintext when neither `cert_path` nor
+/// `key_path` is set; when both are set it terminates TLS itself so GitHub
+/// can deliver webhooks directly without a reverse proxy in front. Setting
+/// only one of the two is a startup error.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct ServerConfig {
+    #[serde(default = "default_listen_addr")]
+    listen_addr: String,
+    cert_path: Option<String>,
+    key_path: Option<String>,
+    debug_addr: Option<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            listen_addr: default_listen_addr(),
+            cert_path: None,
+            key_path: None,
+            debug_addr: None,
+        }
+    }
+}
+
+fn default_listen_addr() -> String {
+    "0.0.0.0:8081".to_string()
+}
+
 #[derive(Debug)]
 pub struct Route {
     route: String,
     hooks: Vec<HookConfig>,
     channel: Mutex<mpsc::Sender<Event>>,
+    db: DbCtx,
 }
 
 impl Route {
-    pub fn new(route: String, channel: mpsc::Sender<Event>) -> Self {
+    pub fn new(route: String, channel: mpsc::Sender<Event>, db: DbCtx) -> Self {
         Route {
             route,
             hooks: vec![],
             channel: Mutex::new(channel),
+            db,
         }
     }
 
@@ -66,7 +117,26 @@ pub enum RouteError {
     GitRepositoryError(GitRepositoryError),
     DecodingError(serde_json::Error),
     AuthenticationError(String),
+    /// The payload matched a configured hook's forge but was missing or
+    /// misshaped a field the server needs, e.g. `GithubHookError`'s `Display`.
+    BadPayload(String),
     ChannelError,
+    DbError(crate::db::DbError),
+}
+
+impl RouteError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            RouteError::BadPayload(_) => StatusCode::BadRequest,
+            _ => StatusCode::InternalServerError,
+        }
+    }
+}
+
+impl From<crate::db::DbError> for RouteError {
+    fn from(e: crate::db::DbError) -> Self {
+        RouteError::DbError(e)
+    }
 }
 
 impl From<GitRepositoryError> for RouteError {
@@ -165,70 +235,65 @@ fn handle_command(
     handle_command_result(repo.run_command(cmd, no_args), stage, log)
 }
 
+/// Whether a job in `state` still needs to be queued for a worker run.
+/// A webhook redelivery of a job already `Running` or `Finished` must not
+/// re-run it - only a fresh `Pending` row or one that previously `Error`ed
+/// out (e.g. the worker panicked) needs to be retried.
+fn should_enqueue(state: JobState) -> bool {
+    !matches!(state, JobState::Running | JobState::Finished { .. })
+}
+
 impl Route {
     pub fn route(&self) -> String {
         self.route.clone()
     }
 
-    fn validate_signature(
-        &self,
-        hook: &HookConfig,
-        req: &mut Request<()>,
-        body: &[u8],
-    ) -> Result<(), String> {
-        if let Some(secret) = &hook.secret {
-            // signature = 'sha256=' + OpenSSL::HMAC.hexdigest(OpenSSL::Digest.new('sha256'), ENV['SECRET_TOKEN'], payload_body)
-            // return halt 500, "Signatures didn't match!" unless Rack::Utils.secure_compare(signature, request.env['HTTP_X_HUB_SIGNATURE_256'])
-
-            let signature = req
-                .header("X-Hub-Signature-256")
-                .ok_or_else(|| "Missing X-Hub-Signature-256 header".to_string())?
-                .last()
-                .as_str();
-
-            let signature = signature.strip_prefix("sha256=").ok_or_else(|| {
-                format!(
-                    "Malformed HTTP_X_HUB_SIGNATURE_256: should start with sha256= but was '{}'",
-                    signature
-                )
-            })?;
-
-            println!("Signature from headers is '{}'", signature);
-
-            let signature_bytes = hex::decode(signature).map_err(|_| {
-                format!(
-                    "Malformed X-Hub-Signature-256: should be all hex, but was '{}'",
-                    signature
-                )
-            })?;
-
-            use ring::hmac;
-            let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
-
-            let tag = hmac::sign(&key, body);
-            println!(
-                "signature from key and body is {}",
-                hex::encode(tag.as_ref())
-            );
-
-            hmac::verify(&key, &body, &signature_bytes)
-                .map_err(|_| "Invalid message signature".to_string())?;
-        }
-        Ok(())
-    }
-
-    fn hook_for_event(&self, v: &GithubPushEvent) -> Option<&HookConfig> {
+    /// Finds the hook (and its normalized push) this request is for by
+    /// trying each configured hook's forge against the raw body until one
+    /// both understands the payload and matches the hook's repo/branch.
+    ///
+    /// A route can mix hooks from different forges (that's the point of
+    /// `ForgeType` - one route serving both a github.com repo and a
+    /// self-hosted one), so one hook's forge erroring on a payload it
+    /// doesn't actually own must not stop the remaining hooks from being
+    /// tried. Only surfaces `Err` - a schema change worth a 400 - once
+    /// nothing on the route has matched.
+    fn hook_and_push_for_body(&self, body: &[u8]) -> Result<Option<(&HookConfig, PushInfo)>, String> {
+        let mut first_err = None;
         for hook in &self.hooks {
-            if v.repository.full_name == hook.repo_name
-                && v.reference.0 == format!("refs/heads/{}", hook.branch)
+            let info = match hook.forge_type.forge().parse_push(body) {
+                Ok(Some(info)) => info,
+                Ok(None) => continue,
+                Err(e) => {
+                    first_err.get_or_insert(e);
+                    continue;
+                }
+            };
+            if info.repo_full_name == hook.repo_name
+                && info.git_ref == format!("refs/heads/{}", hook.branch)
             {
-                return Some(hook);
+                return Ok(Some((hook, info)));
             }
         }
-        None
+        match first_err {
+            Some(e) => Err(e),
+            None => Ok(None),
+        }
     }
 
     pub async fn process_request(&self, req: &mut Request<()>) -> Result<(), RouteError> {
+        // GitHub sends other event types to the same webhook URL (a `ping`
+        // on "Add webhook", issue/PR events if the hook is ever broadened,
+        // etc). We only know how to act on `push`, so anything else is
+        // ignored rather than treated as an invalid payload.
+        if let Some(values) = req.header("X-GitHub-Event") {
+            let event = values.last().as_str();
+            if event != "push" {
+                eprintln!("Ignoring GitHub event '{}' (not a push)", event);
+                return Ok(());
+            }
+        }
+
         // We cant use the body_json method directly as we need to get the raw bytes to check the
         // secret is correct. But we can't validate the body until we've built the object
         // since we dont know which hook it corresponds to.
@@ -241,17 +306,7 @@ impl Route {
             }
         };
 
-        let v = serde_json::from_slice(&body);
-        let v: GithubPushEvent = match v {
-            Ok(v) => v,
-            Err(e) => {
-                eprintln!("Error decoding GithubPushEvent:\n{:?}\n", e);
-                return Err(RouteError::DecodingError(e));
-            }
-        };
-        eprintln!("Got GithubPushEvent:\n{:#?}\n", v);
-
-        let hook: &HookConfig = match self.hook_for_event(&v) {
+        let (hook, info) = match self.hook_and_push_for_body(&body).map_err(RouteError::BadPayload)? {
             Some(v) => v,
             None => {
                 eprintln!("No valid hook found");
@@ -260,26 +315,46 @@ impl Route {
                 ));
             }
         };
-        eprintln!("Using hook: {}", hook.name);
+        eprintln!("Using hook: {} ({:?})", hook.name, info);
 
-        if let Err(e) = self.validate_signature(hook, req, &body) {
-            eprintln!("Error validating webhook:\n{:?}\n", e);
-            return Err(RouteError::AuthenticationError(e));
+        if let Some(secret) = &hook.secret {
+            if let Err(e) = hook.forge_type.forge().verify_signature(req, &body, secret) {
+                eprintln!("Error validating webhook:\n{:?}\n", e);
+                return Err(RouteError::AuthenticationError(e));
+            }
+        }
+
+        let (db_id, state) = self.db.insert_job(
+            &hook.name,
+            &info.repo_full_name,
+            &info.git_ref,
+            &info.after_sha,
+            std::str::from_utf8(&body).unwrap_or(""),
+        )?;
+
+        // GitHub redelivers (timeouts, a manual "Redeliver", its documented
+        // at-least-once delivery) land here as the same (hook, repo, ref,
+        // sha) key. Only a job that's still Pending or previously Errored
+        // needs to actually run - Running/Finished already has the state
+        // and log a redelivery would otherwise overwrite.
+        if !should_enqueue(state) {
+            eprintln!(
+                "Job {} for hook {} is already {:?} - ignoring redelivery",
+                db_id, hook.name, state
+            );
+            return Ok(());
         }
 
         self.channel.lock().map_err(|_e| RouteError::ChannelError)?
             .send(
                 Event::PushEvent( PushEvent{
                     hook: hook.clone(),
-                    content: v
+                    content: info,
+                    raw_payload: std::str::from_utf8(&body).unwrap_or("").to_string(),
+                    db_id,
                 })
             ).map_err(|_e| RouteError::ChannelError)?;
 
-        // TODO: Validate that this event is for the repository we care about
-        //       and the branches we care about.
-
-
-
         Ok(())
     }
 }
@@ -292,7 +367,7 @@ impl Endpoint<()> for Route {
             Ok(_) => Ok("".into()),
             Err(e) => {
                 eprintln!("Error processing request: {:?}", e);
-                let mut res = Response::new(StatusCode::InternalServerError);
+                let mut res = Response::new(e.status_code());
                 res.set_body(format!("{:?}", e));
                 Ok(res)
             }
@@ -302,7 +377,9 @@ impl Endpoint<()> for Route {
 
 pub struct PushEvent {
     hook: HookConfig,
-    content: GithubPushEvent,
+    content: PushInfo,
+    raw_payload: String,
+    db_id: i64,
 }
 
 pub enum Event {
@@ -310,7 +387,59 @@ pub enum Event {
     PushEvent(PushEvent),
 }
 
-fn update_and_run_hook(hook: &HookConfig) -> Result<(), RouteError> {
+fn report_status(hook: &HookConfig, raw_payload: &str, state: StatusState, description: &str) {
+    if hook.forge_type != ForgeType::Github {
+        return;
+    }
+    let token = match &hook.github_token {
+        Some(token) => token,
+        None => return,
+    };
+    let summary = match GithubPushSummary::parse(raw_payload.as_bytes()) {
+        Ok(summary) => summary,
+        Err(e) => {
+            eprintln!("Error re-parsing push payload for status reporting: {:?}", e);
+            return;
+        }
+    };
+    let statuses_url = format!(
+        "https://api.github.com/repos/{}/statuses/{}",
+        summary.repo_full_name, summary.after_sha
+    );
+
+    let client = GithubClient::new(token.clone());
+    let result = async_std::task::block_on(client.create_status(
+        &statuses_url,
+        state,
+        description,
+        &hook.name,
+        None,
+    ));
+    if let Err(e) = result {
+        eprintln!(
+            "Error reporting status for hook {} on {}: {:?}",
+            hook.name, statuses_url, e
+        );
+    }
+}
+
+/// Best-effort commit subject/author for an email notification. Only
+/// GitHub payloads are read for `head_commit`; hooks on other forges get
+/// placeholders rather than failing the notification outright.
+fn commit_summary(hook: &HookConfig, raw_payload: &str) -> (String, String) {
+    if hook.forge_type != ForgeType::Github {
+        return ("(unknown)".to_string(), "(unknown)".to_string());
+    }
+    match GithubPushSummary::parse(raw_payload.as_bytes()) {
+        Ok(summary) => (
+            summary.head_commit_message.unwrap_or_else(|| "(unknown)".to_string()),
+            summary.head_commit_author.unwrap_or_else(|| "(unknown)".to_string()),
+        ),
+        Err(_) => ("(unknown)".to_string(), "(unknown)".to_string()),
+    }
+}
+
+fn update_and_run_hook(hook: &HookConfig, log: &mut SimpleLog) -> Result<(), RouteError> {
     let git = "git";
     let repo = GitRepository {
         repo_dir: hook.repository_directory.clone(),
@@ -318,27 +447,20 @@ fn update_and_run_hook(hook: &HookConfig) -> Result<(), RouteError> {
         main_branch: hook.branch.clone(),
     };
 
-    let mut log = SimpleLog::default();
-
-    handle_git_command(
-        &["fetch", "origin"],
-        "fetching latest changes",
-        &mut log,
-        &repo,
-    )?;
+    handle_git_command(&["fetch", "origin"], "fetching latest changes", log, &repo)?;
     handle_git_command(
         &["checkout", &repo.main_branch],
         "checking out main branch",
-        &mut log,
+        log,
         &repo,
     )?;
     handle_git_command(
         &["rebase", &format!("origin/{}", &repo.main_branch)],
         "rebasing onto latest changes",
-        &mut log,
+        log,
         &repo,
     )?;
-    handle_command(&hook.script, "running hook", &mut log, &repo)?;
+    handle_command(&hook.script, "running hook", log, &repo)?;
     Ok(())
 }
 
@@ -356,36 +478,130 @@ async fn main() -> tide::Result<()> {
         }
     }
 
+    let db = DbCtx::open(&config.db_path)
+        .with_context(|| format!("Unable to open job database {}", config.db_path))?;
+
     // TODO: Consolidate repos with the same route - they should be OK
     //       we can differentiate them based on what github returns in the
     //       webhook.
     let (send, recv) = std::sync::mpsc::channel::<Event>();
 
+    // Crash recovery: anything still Pending/Running when we last stopped
+    // didn't finish, so re-enqueue it now rather than silently losing it.
+    for job in db
+        .unfinished_jobs()
+        .with_context(|| "Unable to read unfinished jobs from the database")?
+    {
+        let hook = config.hooks.iter().find(|h| h.name == job.hook_name);
+        let hook = match hook {
+            Some(hook) => hook.clone(),
+            None => {
+                eprintln!(
+                    "Skipping recovery of job {} - no hook named '{}' in config",
+                    job.id, job.hook_name
+                );
+                continue;
+            }
+        };
+        let content = match hook.forge_type.forge().parse_push(job.payload.as_bytes()) {
+            Ok(Some(content)) => content,
+            Ok(None) | Err(_) => {
+                eprintln!("Skipping recovery of job {} - unparseable payload", job.id);
+                continue;
+            }
+        };
+        println!("Re-enqueueing job {} for hook '{}'", job.id, hook.name);
+        send.send(Event::PushEvent(PushEvent {
+            hook,
+            content,
+            raw_payload: job.payload,
+            db_id: job.id,
+        }))?;
+    }
+
     let mut routes: BTreeMap<String, Route> = BTreeMap::new();
 
     for hook in config.hooks {
         routes
             .entry(hook.hook_route.clone())
-            .or_insert_with(|| Route::new(hook.hook_route.clone(), send.clone()))
+            .or_insert_with(|| Route::new(hook.hook_route.clone(), send.clone(), db.clone()))
             .add_hook(hook);
     }
 
+    let worker_db = db.clone();
     let h = std::thread::spawn(
         move || {
             loop {
                 match recv.recv().unwrap() {
                     Event::Done => break,
                     Event::PushEvent(event) => {
-                        // TODO: We should check the state for this entry in the DB
-                        println!("Processing event {}", event.db_id);
+                        println!("Processing event {} (job {})", event.hook.name, event.db_id);
                         println!("{:?}", event.content);
-                        match update_and_run_hook(&event.hook) {
-                            Ok(()) => {}
-                            Err(e) => {
+
+                        if let Err(e) = worker_db.set_state(event.db_id, JobState::Running) {
+                            eprintln!("Error recording job {} as running: {:?}", event.db_id, e);
+                        }
+                        report_status(
+                            &event.hook,
+                            &event.raw_payload,
+                            StatusState::Pending,
+                            "Build started",
+                        );
+
+                        let mut log = SimpleLog::default();
+                        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                            update_and_run_hook(&event.hook, &mut log)
+                        }));
+
+                        let state = match &result {
+                            Ok(Ok(())) => JobState::Finished { success: true },
+                            Ok(Err(e)) => {
                                 eprintln!("Error running hook {}: {:?}", event.hook.name, e);
+                                JobState::Finished { success: false }
+                            }
+                            Err(_) => {
+                                eprintln!("Hook {} panicked", event.hook.name);
+                                JobState::Error
+                            }
+                        };
+                        if let Err(e) = worker_db.set_state(event.db_id, state) {
+                            eprintln!("Error recording job {} outcome: {:?}", event.db_id, e);
+                        }
+                        let (status_state, description) = match &result {
+                            Ok(Ok(())) => (StatusState::Success, "Build succeeded"),
+                            Ok(Err(_)) => (StatusState::Failure, "Build failed"),
+                            Err(_) => (StatusState::Error, "Build worker panicked"),
+                        };
+                        report_status(&event.hook, &event.raw_payload, status_state, description);
+                        if let Err(e) = worker_db.set_log(event.db_id, &log.content) {
+                            eprintln!("Error recording job {} log: {:?}", event.db_id, e);
+                        }
+
+                        let success = matches!(result, Ok(Ok(())));
+                        if let Some(notify_cfg) = event.hook.notify.clone() {
+                            if notify_cfg.should_notify(success) {
+                                let (commit_subject, commit_author) =
+                                    commit_summary(&event.hook, &event.raw_payload);
+                                let repo_full_name = event.content.repo_full_name.clone();
+                                let branch = event.hook.branch.clone();
+                                let log_content = log.content.clone();
+                                async_std::task::spawn(async move {
+                                    if let Err(e) = notify::send_hook_result_email(
+                                        &notify_cfg,
+                                        &repo_full_name,
+                                        &branch,
+                                        &commit_subject,
+                                        &commit_author,
+                                        success,
+                                        &log_content,
+                                    )
+                                    .await
+                                    {
+                                        eprintln!("Error sending notification email: {:?}", e);
+                                    }
+                                });
                             }
                         }
-                        // TODO: We should update the state for this entry in the DB
                     }
                 }
             }
@@ -399,7 +615,46 @@ async fn main() -> tide::Result<()> {
         app.at(&route.route()).post(route);
     }
 
-    app.listen("0.0.0.0:8081").await?;
+    match (&config.server.cert_path, &config.server.key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            println!(
+                "Listening on {} (TLS, cert = {})",
+                config.server.listen_addr, cert_path
+            );
+            let tls_listener = tide_rustls::TlsListener::build()
+                .addrs(&config.server.listen_addr)
+                .cert(cert_path)
+                .key(key_path);
+            match &config.server.debug_addr {
+                Some(debug_addr) => {
+                    println!("Also listening on {} (plaintext, debug)", debug_addr);
+                    app.listen((tls_listener, debug_addr.as_str())).await?;
+                }
+                None => {
+                    app.listen(tls_listener).await?;
+                }
+            }
+        }
+        (None, None) => {
+            println!("Listening on {} (plaintext)", config.server.listen_addr);
+            match &config.server.debug_addr {
+                Some(debug_addr) => {
+                    println!("Also listening on {} (plaintext, debug)", debug_addr);
+                    app.listen((config.server.listen_addr.as_str(), debug_addr.as_str()))
+                        .await?;
+                }
+                None => {
+                    app.listen(config.server.listen_addr.as_str()).await?;
+                }
+            }
+        }
+        (Some(_), None) => {
+            bail!("server.cert_path is set but server.key_path is missing - refusing to fall back to plaintext")
+        }
+        (None, Some(_)) => {
+            bail!("server.key_path is set but server.cert_path is missing - refusing to fall back to plaintext")
+        }
+    }
 
     send.send(Event::Done)?;
 
@@ -407,3 +662,104 @@ async fn main() -> tide::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_hook(name: &str, forge_type: ForgeType) -> HookConfig {
+        HookConfig {
+            name: name.to_string(),
+            repo_name: "org/repo".to_string(),
+            hook_route: "/hook".to_string(),
+            repository_directory: "/tmp/repo".to_string(),
+            script: "true".to_string(),
+            branch: "main".to_string(),
+            secret: None,
+            github_token: None,
+            forge_type,
+            notify: None,
+        }
+    }
+
+    fn test_route(hooks: Vec<HookConfig>) -> Route {
+        let (send, _recv) = mpsc::channel();
+        let mut route = Route::new("/hook".to_string(), send, DbCtx::open(":memory:").unwrap());
+        for hook in hooks {
+            route.add_hook(hook);
+        }
+        route
+    }
+
+    #[test]
+    fn hook_and_push_for_body_skips_a_hook_whose_forge_errors_on_the_payload() {
+        // A route serving both a github.com repo and a self-hosted GitLab
+        // repo: a genuine GitLab push (no top-level `repository`) must not
+        // be dropped just because the GitHub hook is tried first and its
+        // forge errors out on the mismatched shape.
+        let route = test_route(vec![
+            test_hook("gh", ForgeType::Github),
+            test_hook("gl", ForgeType::Gitlab),
+        ]);
+        let body = serde_json::json!({
+            "object_kind": "push",
+            "ref": "refs/heads/main",
+            "after": "abc123",
+            "project": { "path_with_namespace": "org/repo" },
+        })
+        .to_string();
+
+        let (hook, info) = route
+            .hook_and_push_for_body(body.as_bytes())
+            .unwrap()
+            .expect("gitlab hook should have matched");
+        assert_eq!(hook.name, "gl");
+        assert_eq!(info.repo_full_name, "org/repo");
+    }
+
+    #[test]
+    fn hook_and_push_for_body_returns_none_when_nothing_matches() {
+        let route = test_route(vec![test_hook("gh", ForgeType::Github)]);
+        let body = serde_json::json!({
+            "ref": "refs/heads/main",
+            "after": "abc123",
+            "repository": { "full_name": "org/some-other-repo" },
+        })
+        .to_string();
+
+        assert!(route.hook_and_push_for_body(body.as_bytes()).unwrap().is_none());
+    }
+
+    #[test]
+    fn hook_and_push_for_body_surfaces_error_when_nothing_matches() {
+        let route = test_route(vec![test_hook("gh", ForgeType::Github)]);
+        let body = serde_json::json!({ "ref": "refs/heads/main", "after": "abc123" }).to_string();
+
+        assert!(route.hook_and_push_for_body(body.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn should_enqueue_a_fresh_pending_job() {
+        assert!(should_enqueue(JobState::Pending));
+    }
+
+    #[test]
+    fn should_enqueue_a_job_that_previously_errored() {
+        assert!(should_enqueue(JobState::Error));
+    }
+
+    #[test]
+    fn should_not_enqueue_a_running_job() {
+        // A webhook redelivery for a job already in flight must not kick
+        // off a second run alongside it.
+        assert!(!should_enqueue(JobState::Running));
+    }
+
+    #[test]
+    fn should_not_enqueue_a_finished_job() {
+        // A webhook redelivery for an already-built SHA must not overwrite
+        // the finished job's state/log with a re-run.
+        assert!(!should_enqueue(JobState::Finished { success: true }));
+        assert!(!should_enqueue(JobState::Finished { success: false }));
+    }
+}
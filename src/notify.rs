@@ -0,0 +1,119 @@
+use lettre::message::Message;
+use lettre::transport::smtp::AsyncSmtpTransport;
+use lettre::{AsyncStd1Executor, AsyncTransport};
+use serde::Deserialize;
+
+/// When to send a notification email for a hook run.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyPolicy {
+    Always,
+    OnFailureOnly,
+}
+
+impl Default for NotifyPolicy {
+    fn default() -> Self {
+        NotifyPolicy::Always
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct NotifyConfig {
+    pub smtp_url: String,
+    pub from: String,
+    pub recipients: Vec<String>,
+    #[serde(default)]
+    pub policy: NotifyPolicy,
+}
+
+impl NotifyConfig {
+    pub fn should_notify(&self, success: bool) -> bool {
+        match self.policy {
+            NotifyPolicy::Always => true,
+            NotifyPolicy::OnFailureOnly => !success,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum NotifyError {
+    BuildError(String),
+    SendError(String),
+}
+
+/// Emails `cfg.recipients` a summary of a finished hook run: repo, branch,
+/// head commit, pass/fail, and the full captured log. Callers should run
+/// this on a spawned task rather than awaiting it inline, so a stalled SMTP
+/// server can't wedge the caller.
+pub async fn send_hook_result_email(
+    cfg: &NotifyConfig,
+    repo_full_name: &str,
+    branch: &str,
+    commit_subject: &str,
+    commit_author: &str,
+    success: bool,
+    log: &str,
+) -> Result<(), NotifyError> {
+    let status = if success { "SUCCESS" } else { "FAILURE" };
+    let subject = format!("[{}] {} ({}): {}", status, repo_full_name, branch, commit_subject);
+    let body = format!(
+        "Repository: {}\nBranch: {}\nCommit: {} ({})\nStatus: {}\n\n{}",
+        repo_full_name, branch, commit_subject, commit_author, status, log
+    );
+
+    let mut builder = Message::builder()
+        .from(
+            cfg.from
+                .parse()
+                .map_err(|e| NotifyError::BuildError(format!("bad from address: {}", e)))?,
+        )
+        .subject(subject);
+    for recipient in &cfg.recipients {
+        builder = builder.to(recipient
+            .parse()
+            .map_err(|e| NotifyError::BuildError(format!("bad recipient address: {}", e)))?);
+    }
+    let email = builder
+        .body(body)
+        .map_err(|e| NotifyError::BuildError(e.to_string()))?;
+
+    let mailer = AsyncSmtpTransport::<AsyncStd1Executor>::from_url(&cfg.smtp_url)
+        .map_err(|e| NotifyError::BuildError(e.to_string()))?
+        .build();
+
+    mailer
+        .send(email)
+        .await
+        .map_err(|e| NotifyError::SendError(e.to_string()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_cfg(policy: NotifyPolicy) -> NotifyConfig {
+        NotifyConfig {
+            smtp_url: "smtp://localhost".to_string(),
+            from: "ci@example.com".to_string(),
+            recipients: vec!["dev@example.com".to_string()],
+            policy,
+        }
+    }
+
+    #[test]
+    fn always_notifies_on_success_and_failure() {
+        let cfg = test_cfg(NotifyPolicy::Always);
+        assert!(cfg.should_notify(true));
+        assert!(cfg.should_notify(false));
+    }
+
+    #[test]
+    fn on_failure_only_notifies_only_on_failure() {
+        let cfg = test_cfg(NotifyPolicy::OnFailureOnly);
+        assert!(!cfg.should_notify(true));
+        assert!(cfg.should_notify(false));
+    }
+}